@@ -0,0 +1,313 @@
+//! Streaming large values across multiple objects.
+//!
+//! A single [`super::Object`] is capped at [`BLOCK_SIZE`], so a value
+//! larger than one block has to be sliced across several objects. A
+//! [`BufferedStreamWriter`] does that slicing transparently behind a
+//! plain `Write` impl, and a [`BufferedStreamReader`] turns the
+//! resulting [`Stream`] descriptor back into a `Read + Seek` handle.
+
+use super::HEADER_SIZE;
+use super::{ObjectReader, ObjectStore, Result};
+use crate::chunks::ChunkPointer;
+use crate::crypto::{secure_hash, Tag};
+use crate::BLOCK_SIZE;
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem::size_of;
+use std::sync::Arc;
+
+/// Usable payload capacity of a single object: the full block, minus
+/// the trailing tag and the leading header.
+const OBJECT_CAPACITY: usize = BLOCK_SIZE - size_of::<Tag>() - HEADER_SIZE;
+
+/// zlib/deflate's documented worst case for how much a single
+/// compression call can expand its input: `len + len / 1000 + 12`
+/// bytes. Incompressible payloads (media, already-compressed or
+/// encrypted data) are routine input for a backup tool, so this has
+/// to be budgeted for up front rather than assumed away.
+const fn compressed_worst_case(len: usize) -> usize {
+    len + len / 1000 + 12
+}
+
+/// The largest plaintext slice guaranteed to fit in a single object
+/// even in the worst case where compression doesn't shrink it at all
+/// (or grows it slightly) — the largest `n` for which
+/// `compressed_worst_case(n) <= OBJECT_CAPACITY`. Chunking at
+/// `OBJECT_CAPACITY` itself would trip `ChunkTooLarge` on exactly the
+/// incompressible chunks this stream needs to handle.
+const MAX_CHUNK_SIZE: usize = (OBJECT_CAPACITY.saturating_sub(12) * 1000) / 1001;
+
+const _: () = assert!(compressed_worst_case(MAX_CHUNK_SIZE) <= OBJECT_CAPACITY);
+
+/// Serializable record of the chunks that make up a value too large to
+/// fit in a single object.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Stream {
+    size: u64,
+    chunks: Vec<Arc<ChunkPointer>>,
+}
+
+impl Stream {
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+}
+
+/// Slices an arbitrary-length `Write`r into `BLOCK_SIZE` chunks and
+/// stores each one through an [`ObjectStore`], accumulating the
+/// resulting pointers into a [`Stream`].
+pub struct BufferedStreamWriter<S> {
+    store: S,
+    buffer: Vec<u8>,
+    chunks: Vec<Arc<ChunkPointer>>,
+    size: u64,
+}
+
+impl<S> BufferedStreamWriter<S>
+where
+    S: ObjectStore,
+{
+    pub fn new(store: S) -> BufferedStreamWriter<S> {
+        BufferedStreamWriter {
+            store,
+            buffer: Vec::with_capacity(MAX_CHUNK_SIZE),
+            chunks: vec![],
+            size: 0,
+        }
+    }
+
+    fn store_buffered(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let hash = secure_hash(&self.buffer);
+        let pointer = self.store.store_chunk(&hash, &self.buffer)?;
+        self.chunks.push(pointer);
+        self.buffer.clear();
+
+        Ok(())
+    }
+
+    /// Flush any buffered tail bytes and return the descriptor for
+    /// everything written so far.
+    pub fn finish(mut self) -> Result<Stream> {
+        self.store_buffered()?;
+        self.store.flush()?;
+
+        Ok(Stream {
+            size: self.size,
+            chunks: self.chunks,
+        })
+    }
+}
+
+impl<S> Write for BufferedStreamWriter<S>
+where
+    S: ObjectStore,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        let mut written = 0;
+
+        while !rest.is_empty() {
+            let space = MAX_CHUNK_SIZE - self.buffer.len();
+            let take = space.min(rest.len());
+
+            self.buffer.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+            written += take;
+            self.size += take as u64;
+
+            if self.buffer.len() == MAX_CHUNK_SIZE {
+                self.store_buffered()
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            }
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Presents a [`Stream`] as a contiguous `Read + Seek` handle, fetching
+/// and decrypting the backing chunks on demand.
+pub struct BufferedStreamReader<R> {
+    reader: R,
+    stream: Stream,
+    position: u64,
+    cache: Option<(usize, Vec<u8>)>,
+}
+
+impl<R> BufferedStreamReader<R>
+where
+    R: ObjectReader,
+{
+    pub fn new(reader: R, stream: Stream) -> BufferedStreamReader<R> {
+        BufferedStreamReader {
+            reader,
+            stream,
+            position: 0,
+            cache: None,
+        }
+    }
+
+    fn chunk(&mut self, index: usize) -> Result<&[u8]> {
+        let cached = matches!(&self.cache, Some((i, _)) if *i == index);
+
+        if !cached {
+            let pointer = &self.stream.chunks[index];
+            let mut buf = vec![0u8; MAX_CHUNK_SIZE];
+            let len = self.reader.read_chunk(pointer, &mut buf)?;
+            buf.truncate(len);
+            self.cache = Some((index, buf));
+        }
+
+        Ok(&self.cache.as_ref().expect("just populated the cache").1)
+    }
+}
+
+impl<R> Read for BufferedStreamReader<R>
+where
+    R: ObjectReader,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.stream.size {
+            return Ok(0);
+        }
+
+        let index = (self.position / MAX_CHUNK_SIZE as u64) as usize;
+        let offset_in_chunk = (self.position % MAX_CHUNK_SIZE as u64) as usize;
+
+        let chunk = self
+            .chunk(index)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let available = &chunk[offset_in_chunk..];
+
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.position += n as u64;
+
+        Ok(n)
+    }
+}
+
+impl<R> Seek for BufferedStreamReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(s) => s as i64,
+            SeekFrom::End(e) => self.stream.size as i64 + e,
+            SeekFrom::Current(c) => self.position as i64 + c,
+        };
+
+        if new_pos < 0 {
+            return Err(io::Error::from(io::ErrorKind::InvalidInput));
+        }
+
+        self.position = new_pos as u64;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::*;
+    use std::sync::Mutex;
+
+    /// In-memory stand-in for a real `ObjectStore`: keeps each stored
+    /// chunk around by index instead of persisting it through a
+    /// backend, so these tests exercise the chunking/seek math without
+    /// needing a real backend or crypto provider.
+    #[derive(Clone, Default)]
+    struct FakeStore(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl ObjectStore for FakeStore {
+        fn store_chunk(&mut self, _hash: &CryptoDigest, data: &[u8]) -> Result<Arc<ChunkPointer>> {
+            let mut chunks = self.0.lock().unwrap();
+            let offs = chunks.len() as u32;
+            chunks.push(data.to_vec());
+
+            Ok(Arc::new(ChunkPointer {
+                offs,
+                size: data.len() as u32,
+                ..Default::default()
+            }))
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    struct FakeReader(Vec<Vec<u8>>);
+
+    impl ObjectReader for FakeReader {
+        fn read_chunk(&mut self, pointer: &ChunkPointer, target: &mut [u8]) -> Result<usize> {
+            let data = &self.0[pointer.offs as usize];
+            target[..data.len()].copy_from_slice(data);
+            Ok(data.len())
+        }
+    }
+
+    fn write_spanning_data() -> (Vec<u8>, Stream, Vec<Vec<u8>>) {
+        let store = FakeStore::default();
+        let backing = store.0.clone();
+
+        let mut writer = BufferedStreamWriter::new(store);
+        let data: Vec<u8> = (0..MAX_CHUNK_SIZE + 10).map(|i| (i % 256) as u8).collect();
+        writer.write_all(&data).unwrap();
+        let stream = writer.finish().unwrap();
+
+        let chunks = backing.lock().unwrap().clone();
+        (data, stream, chunks)
+    }
+
+    #[test]
+    fn round_trips_across_chunk_boundary() {
+        let (data, stream, chunks) = write_spanning_data();
+        assert_eq!(stream.len(), data.len() as u64);
+        assert_eq!(stream.chunks.len(), 2);
+
+        let mut reader = BufferedStreamReader::new(FakeReader(chunks), stream);
+        let mut out = vec![0u8; data.len()];
+        reader.read_exact(&mut out).unwrap();
+
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn seeks_across_chunk_boundary() {
+        let (data, stream, chunks) = write_spanning_data();
+        let mut reader = BufferedStreamReader::new(FakeReader(chunks), stream);
+
+        reader
+            .seek(SeekFrom::Start((MAX_CHUNK_SIZE - 3) as u64))
+            .unwrap();
+
+        let mut out = [0u8; 6];
+        reader.read_exact(&mut out).unwrap();
+
+        assert_eq!(out, data[MAX_CHUNK_SIZE - 3..MAX_CHUNK_SIZE + 3]);
+    }
+
+    #[test]
+    fn seek_from_end_lands_on_last_byte() {
+        let (data, stream, chunks) = write_spanning_data();
+        let mut reader = BufferedStreamReader::new(FakeReader(chunks), stream);
+
+        reader.seek(SeekFrom::End(-1)).unwrap();
+
+        let mut out = [0u8; 1];
+        reader.read_exact(&mut out).unwrap();
+
+        assert_eq!(out[0], *data.last().unwrap());
+    }
+}
@@ -0,0 +1,126 @@
+//! Versioned, magic-tagged object container format.
+//!
+//! Borrowed from PNG's signature trick (and mbon's use of the same
+//! idea): the header's first byte is non-ASCII so text-mode transfers
+//! show their damage immediately, the middle bytes spell out the
+//! format name, and the trailing `\r\n\x1A\n` sequence catches both
+//! line-ending mangling and truncated transfers. A one-byte version
+//! follows so the on-disk layout can evolve later.
+
+use super::{Object, ObjectError, Result};
+
+pub const MAGIC: [u8; 8] = [0x89, b'Z', b'S', b'T', b'\r', b'\n', 0x1a, b'\n'];
+pub const FORMAT_VERSION: u8 = 1;
+pub const HEADER_SIZE: usize = MAGIC.len() + 1;
+
+impl<T> Object<T> {
+    /// Reserve the leading [`HEADER_SIZE`] bytes for the container
+    /// header so payload written afterwards never overlaps it.
+    pub fn reserve_header(&mut self) {
+        self.cursor = HEADER_SIZE;
+    }
+}
+
+impl<T> Object<T>
+where
+    T: AsMut<[u8]>,
+{
+    /// Write the magic signature and format version at the front of
+    /// the object, ahead of the payload.
+    pub fn write_header(&mut self) {
+        let mut head = [0u8; HEADER_SIZE];
+        head[..MAGIC.len()].copy_from_slice(&MAGIC);
+        head[MAGIC.len()] = FORMAT_VERSION;
+
+        self.write_head(&head);
+    }
+}
+
+impl<T> Object<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Validate the header written by [`Object::write_header`],
+    /// returning the format version it declares.
+    pub fn parse_header(&self) -> Result<u8> {
+        let buf = self.buffer.as_ref();
+
+        if buf.len() < HEADER_SIZE {
+            return Err(ObjectError::BufferTooSmall {
+                min_size: HEADER_SIZE,
+            });
+        }
+
+        if buf[..MAGIC.len()] != MAGIC {
+            return Err(ObjectError::UnknownFormat);
+        }
+
+        let version = buf[MAGIC.len()];
+        if version != FORMAT_VERSION {
+            return Err(ObjectError::UnsupportedVersion { version });
+        }
+
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{BlockBuffer, ObjectId, ReadBuffer};
+
+    fn written_header() -> [u8; HEADER_SIZE] {
+        let mut object = Object::new(BlockBuffer::default());
+        object.reserve_header();
+        object.write_header();
+
+        let mut head = [0u8; HEADER_SIZE];
+        head.copy_from_slice(&object.buffer.as_ref()[..HEADER_SIZE]);
+        head
+    }
+
+    fn reader_for(buf: impl AsRef<[u8]> + Send + Sync + 'static) -> Object<ReadBuffer> {
+        Object::with_id(ObjectId::default(), ReadBuffer::new(buf))
+    }
+
+    #[test]
+    fn header_round_trips() {
+        let head = written_header();
+        let mut buf = vec![0u8; HEADER_SIZE];
+        buf.copy_from_slice(&head);
+
+        assert_eq!(reader_for(buf).parse_header().unwrap(), FORMAT_VERSION);
+    }
+
+    #[test]
+    fn rejects_wrong_magic() {
+        let mut buf = written_header().to_vec();
+        buf[0] = 0x00;
+
+        assert!(matches!(
+            reader_for(buf).parse_header(),
+            Err(ObjectError::UnknownFormat)
+        ));
+    }
+
+    #[test]
+    fn rejects_unsupported_version() {
+        let mut buf = written_header().to_vec();
+        buf[MAGIC.len()] = FORMAT_VERSION + 1;
+
+        assert!(matches!(
+            reader_for(buf).parse_header(),
+            Err(ObjectError::UnsupportedVersion { version }) if version == FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_buffer() {
+        let buf = written_header()[..HEADER_SIZE - 1].to_vec();
+
+        assert!(matches!(
+            reader_for(buf).parse_header(),
+            Err(ObjectError::BufferTooSmall { min_size }) if min_size == HEADER_SIZE
+        ));
+    }
+}
@@ -6,28 +6,187 @@ use crate::crypto::*;
 use crate::BLOCK_SIZE;
 
 use itertools::Itertools;
+#[cfg(not(feature = "no_std"))]
 use thiserror::Error;
 
+// On hosted targets this is plain `std::io`/`std::sync`. With the
+// `no_std` feature, the same trait names are re-exported from
+// `core_io`/`alloc`/`spin` so every impl below compiles unchanged
+// against bare-metal backends that have storage but no `std`.
+//
+// This crate's manifest and crate root aren't part of this tree, so
+// the other half of this feature — declaring `no_std` and its
+// `core_io`/`spin` optional dependencies in Cargo.toml, and gating
+// `#![no_std]`/`extern crate alloc;` in lib.rs — has to land there
+// instead of here.
+#[cfg(not(feature = "no_std"))]
 use std::io::{self, Read, Seek, SeekFrom, Write};
-use std::mem::size_of;
-use std::string::ToString;
+#[cfg(not(feature = "no_std"))]
 use std::sync::{Arc, Mutex};
+#[cfg(not(feature = "no_std"))]
+use std::string::ToString;
 
-#[derive(Error, Debug)]
+#[cfg(feature = "no_std")]
+use alloc::string::ToString;
+#[cfg(feature = "no_std")]
+use alloc::sync::Arc;
+#[cfg(feature = "no_std")]
+use core_io::{self as io, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "no_std")]
+use spin::Mutex;
+#[cfg(feature = "no_std")]
+use alloc::{boxed::Box, format, vec};
+
+#[cfg(not(feature = "no_std"))]
+use std::mem::size_of;
+#[cfg(feature = "no_std")]
+use core::mem::size_of;
+
+// The writer pool parallelizes across OS threads and isn't meaningful
+// on the single-threaded, no-allocator-thread embedded targets the
+// `no_std` feature is for.
+#[cfg(not(feature = "no_std"))]
+mod pool;
+#[cfg(not(feature = "no_std"))]
+pub use pool::{PoolRef, WriterPool};
+
+// Same as `pool`: these still assume a `std`-style backend and heap,
+// and haven't been ported to the aliased `io`/`alloc` traits above.
+#[cfg(not(feature = "no_std"))]
+mod reader;
+#[cfg(not(feature = "no_std"))]
+pub use reader::{AEADReader, ObjectReader};
+
+#[cfg(not(feature = "no_std"))]
+mod bufferedstream;
+#[cfg(not(feature = "no_std"))]
+pub use bufferedstream::{BufferedStreamReader, BufferedStreamWriter, Stream};
+
+mod header;
+pub use header::{FORMAT_VERSION, HEADER_SIZE, MAGIC};
+
+// `thiserror`'s derive implements `std::error::Error`, which isn't
+// available under `no_std`. On that path we derive only `Debug` and
+// hand-write `Display`/`From` below, since `?` only needs the `From`
+// conversions below, not the `std::error::Error` trait itself.
+#[cfg_attr(not(feature = "no_std"), derive(Error))]
+#[derive(Debug)]
 pub enum ObjectError {
-    #[error("IO error")]
+    #[cfg_attr(not(feature = "no_std"), error("IO error"))]
     Io {
-        #[from]
+        #[cfg_attr(not(feature = "no_std"), from)]
         source: io::Error,
     },
-    #[error("Backend error")]
+    #[cfg_attr(not(feature = "no_std"), error("Backend error"))]
     Backend {
-        #[from]
+        #[cfg_attr(not(feature = "no_std"), from)]
         source: BackendError,
     },
+    #[cfg_attr(not(feature = "no_std"), error("Decryption failed"))]
+    Crypto {
+        #[cfg_attr(not(feature = "no_std"), from)]
+        source: CryptoError,
+    },
+    #[cfg_attr(not(feature = "no_std"), error("Compression failed"))]
+    Compress {
+        #[cfg_attr(not(feature = "no_std"), from)]
+        source: compress::CompressError,
+    },
+    #[cfg_attr(not(feature = "no_std"), error("Decompression failed"))]
+    Decompress {
+        #[cfg_attr(not(feature = "no_std"), from)]
+        source: compress::DecompressError,
+    },
+    #[cfg_attr(
+        not(feature = "no_std"),
+        error("chunk too large for a single object: {size} bytes, max {max_size}")
+    )]
+    ChunkTooLarge { max_size: usize, size: usize },
+    #[cfg_attr(
+        not(feature = "no_std"),
+        error("buffer too small, need at least {min_size} bytes")
+    )]
+    BufferTooSmall { min_size: usize },
+    #[cfg_attr(not(feature = "no_std"), error("not a zerostash object"))]
+    UnknownFormat,
+    #[cfg_attr(
+        not(feature = "no_std"),
+        error("unsupported object format version {version}")
+    )]
+    UnsupportedVersion { version: u8 },
+    #[cfg_attr(
+        not(feature = "no_std"),
+        error("chunk pointer ({offs}..{end}) is out of bounds for a {len}-byte object")
+    )]
+    CorruptObject { offs: u32, end: u32, len: usize },
+}
+
+#[cfg(feature = "no_std")]
+impl core::fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ObjectError::Io { .. } => write!(f, "IO error"),
+            ObjectError::Backend { .. } => write!(f, "Backend error"),
+            ObjectError::Crypto { .. } => write!(f, "Decryption failed"),
+            ObjectError::Compress { .. } => write!(f, "Compression failed"),
+            ObjectError::Decompress { .. } => write!(f, "Decompression failed"),
+            ObjectError::ChunkTooLarge { max_size, size } => write!(
+                f,
+                "chunk too large for a single object: {} bytes, max {}",
+                size, max_size
+            ),
+            ObjectError::BufferTooSmall { min_size } => {
+                write!(f, "buffer too small, need at least {} bytes", min_size)
+            }
+            ObjectError::UnknownFormat => write!(f, "not a zerostash object"),
+            ObjectError::UnsupportedVersion { version } => {
+                write!(f, "unsupported object format version {}", version)
+            }
+            ObjectError::CorruptObject { offs, end, len } => write!(
+                f,
+                "chunk pointer ({}..{}) is out of bounds for a {}-byte object",
+                offs, end, len
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "no_std")]
+impl From<io::Error> for ObjectError {
+    fn from(source: io::Error) -> Self {
+        ObjectError::Io { source }
+    }
+}
+
+#[cfg(feature = "no_std")]
+impl From<BackendError> for ObjectError {
+    fn from(source: BackendError) -> Self {
+        ObjectError::Backend { source }
+    }
+}
+
+#[cfg(feature = "no_std")]
+impl From<CryptoError> for ObjectError {
+    fn from(source: CryptoError) -> Self {
+        ObjectError::Crypto { source }
+    }
+}
+
+#[cfg(feature = "no_std")]
+impl From<compress::CompressError> for ObjectError {
+    fn from(source: compress::CompressError) -> Self {
+        ObjectError::Compress { source }
+    }
 }
 
-pub type Result<T> = std::result::Result<T, ObjectError>;
+#[cfg(feature = "no_std")]
+impl From<compress::DecompressError> for ObjectError {
+    fn from(source: compress::DecompressError) -> Self {
+        ObjectError::Decompress { source }
+    }
+}
+
+pub type Result<T> = core::result::Result<T, ObjectError>;
 
 pub trait ObjectStore: Clone + Send {
     fn store_chunk(&mut self, hash: &CryptoDigest, data: &[u8]) -> Result<Arc<ChunkPointer>>;
@@ -199,8 +358,17 @@ where
     }
 
     #[inline(always)]
-    pub fn write_tag(&mut self, buf: &[u8]) {
-        self.buffer.as_mut()[self.capacity..].copy_from_slice(buf);
+    pub fn write_tag(&mut self, buf: &[u8]) -> Result<()> {
+        let region = &mut self.buffer.as_mut()[self.capacity..];
+
+        if buf.len() != region.len() {
+            return Err(ObjectError::BufferTooSmall {
+                min_size: region.len(),
+            });
+        }
+
+        region.copy_from_slice(buf);
+        Ok(())
     }
 
     #[inline(always)]
@@ -222,15 +390,23 @@ where
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let ofs = self.cursor;
         let len = buf.len();
+        let end = ofs + len;
 
-        self.buffer.as_mut()[ofs..(ofs + len)].copy_from_slice(buf);
-        self.cursor += len;
+        if end > self.capacity {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "write would exceed object capacity",
+            ));
+        }
+
+        self.buffer.as_mut()[ofs..end].copy_from_slice(buf);
+        self.cursor = end;
 
         Ok(len)
     }
 
     #[inline(always)]
-    fn flush(&mut self) -> std::io::Result<()> {
+    fn flush(&mut self) -> io::Result<()> {
         Ok(())
     }
 }
@@ -386,6 +562,8 @@ where
     pub fn new(backend: Arc<dyn Backend>, crypto: C) -> Storage<C> {
         let mut object = WriteObject::default();
         object.id.reset(&crypto);
+        object.reserve_tag();
+        object.reserve_header();
 
         let capacity = object.capacity();
         Storage {
@@ -404,6 +582,12 @@ where
     fn store_chunk(&mut self, hash: &CryptoDigest, data: &[u8]) -> Result<Arc<ChunkPointer>> {
         let mut compressed = compress::block(&data)?;
         let size = compressed.len();
+        let max_size = self.capacity - HEADER_SIZE;
+
+        if size > max_size {
+            return Err(ObjectError::ChunkTooLarge { max_size, size });
+        }
+
         let mut offs = self.object.position();
         if offs + size > self.capacity {
             self.flush()?;
@@ -426,11 +610,12 @@ where
     }
 
     fn flush(&mut self) -> Result<()> {
+        self.object.write_header();
         self.object.finalize(&self.crypto);
         self.backend.write_object(&self.object)?;
 
         self.object.id.reset(&self.crypto);
-        self.object.reset_cursor();
+        self.object.reserve_header();
 
         Ok(())
     }
@@ -441,7 +626,15 @@ pub struct NullStorage(pub Arc<Mutex<usize>>);
 
 impl ObjectStore for NullStorage {
     fn store_chunk(&mut self, _hash: &CryptoDigest, data: &[u8]) -> Result<Arc<ChunkPointer>> {
-        *self.0.lock().unwrap() += data.len();
+        #[cfg(not(feature = "no_std"))]
+        {
+            *self.0.lock().unwrap() += data.len();
+        }
+        #[cfg(feature = "no_std")]
+        {
+            *self.0.lock() += data.len();
+        }
+
         Ok(Arc::default())
     }
 
@@ -0,0 +1,298 @@
+//! A fixed-size pool of reusable write buffers, handed out to worker
+//! threads as independent [`PoolRef`] writers so that chunk storage can
+//! be parallelized without serializing everyone through a single
+//! `Storage`.
+
+use super::{BlockBuffer, ObjectId, Result, WriteObject};
+use crate::backends::{Backend, BackendError};
+use crate::chunks::ChunkPointer;
+use crate::compress;
+use crate::crypto::*;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+
+struct Inner<C> {
+    backend: Arc<dyn Backend>,
+    crypto: C,
+    buffers: Mutex<VecDeque<BlockBuffer>>,
+    available: Condvar,
+}
+
+impl<C> Inner<C> {
+    fn checkout(&self) -> BlockBuffer {
+        let mut buffers = self.buffers.lock().unwrap();
+        loop {
+            if let Some(buffer) = buffers.pop_front() {
+                return buffer;
+            }
+            buffers = self.available.wait(buffers).unwrap();
+        }
+    }
+
+    fn checkin(&self, buffer: BlockBuffer) {
+        self.buffers.lock().unwrap().push_back(buffer);
+        self.available.notify_one();
+    }
+}
+
+/// A bounded pool of [`BlockBuffer`]s shared by every [`PoolRef`] checked
+/// out from it. Cloning a `WriterPool` is cheap; every clone draws from
+/// the same fixed set of buffers and the same backend.
+pub struct WriterPool<C> {
+    inner: Arc<Inner<C>>,
+}
+
+impl<C> Clone for WriterPool<C> {
+    fn clone(&self) -> WriterPool<C> {
+        WriterPool {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<C> WriterPool<C>
+where
+    C: CryptoProvider,
+{
+    /// Create a pool of `size` reusable buffers backed by `backend`.
+    ///
+    /// `size` bounds the memory the pool can use at once: once every
+    /// buffer is checked out, the next call to [`WriterPool::writer`] or
+    /// [`PoolRef::flush`] blocks until one is recycled.
+    pub fn new(size: usize, backend: Arc<dyn Backend>, crypto: C) -> WriterPool<C> {
+        let buffers = (0..size.max(1)).map(|_| BlockBuffer::default()).collect();
+
+        WriterPool {
+            inner: Arc::new(Inner {
+                backend,
+                crypto,
+                buffers: Mutex::new(buffers),
+                available: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Check out a buffer and hand back an independent writer. Each
+    /// `PoolRef` allocates its own [`ObjectId`] and can be driven from
+    /// its own worker thread.
+    pub fn writer(&self) -> PoolRef<C> {
+        PoolRef {
+            pool: self.clone(),
+            object: Some(self.fresh_object()),
+        }
+    }
+
+    fn fresh_object(&self) -> WriteObject {
+        let buffer = self.inner.checkout();
+        let mut object = WriteObject::new(buffer);
+        object.id.reset(&self.inner.crypto);
+        object.reserve_tag();
+        object.reserve_header();
+        object
+    }
+}
+
+/// A pooled [`WriteObject`] checked out from a [`WriterPool`].
+///
+/// `PoolRef` stores chunks exactly like [`super::Storage`], except that
+/// flushing an object returns its buffer to the pool and checks out a
+/// fresh one, rather than reusing a single `Storage`-owned buffer.
+///
+/// `object` is only ever `None` for the instant between handing the
+/// spent buffer back to the pool and checking out its replacement in
+/// [`PoolRef::flush`]/[`Drop`] — it lets us give up the current buffer
+/// without needing a throwaway placeholder allocation.
+pub struct PoolRef<C> {
+    pool: WriterPool<C>,
+    object: Option<WriteObject>,
+}
+
+impl<C> PoolRef<C>
+where
+    C: CryptoProvider,
+{
+    fn object(&self) -> &WriteObject {
+        self.object.as_ref().expect("PoolRef used after drop")
+    }
+
+    pub fn store_chunk(&mut self, hash: &CryptoDigest, data: &[u8]) -> Result<Arc<ChunkPointer>> {
+        let mut compressed = compress::block(data)?;
+        let size = compressed.len();
+        let max_size = self.object().capacity() - super::HEADER_SIZE;
+
+        if size > max_size {
+            return Err(super::ObjectError::ChunkTooLarge { max_size, size });
+        }
+
+        let mut offs = self.object().position();
+        if offs + size > self.object().capacity() {
+            self.flush()?;
+            offs = self.object().position();
+        }
+
+        let object = self.object.as_mut().expect("PoolRef used after drop");
+        let tag = self.pool.inner.crypto.encrypt_chunk(object, hash, &mut compressed);
+
+        object.write_all(&compressed)?;
+
+        Ok(Arc::new(ChunkPointer {
+            offs: offs as u32,
+            size: size as u32,
+            file: object.id,
+            hash: *hash,
+            tag,
+        }))
+    }
+
+    /// Persist the current object and recycle its buffer back into the
+    /// pool, checking out a fresh one for subsequent writes.
+    ///
+    /// The spent buffer is returned to the pool *before* a replacement
+    /// is checked out: under a fully-subscribed pool every `PoolRef` is
+    /// holding a buffer, so checking out first (while still holding
+    /// ours) would wait forever for a buffer only our own `checkin`
+    /// could supply.
+    pub fn flush(&mut self) -> Result<()> {
+        let mut object = self.object.take().expect("PoolRef used after drop");
+
+        object.write_header();
+        object.finalize(&self.pool.inner.crypto);
+        self.pool.inner.backend.write_object(&object)?;
+
+        self.pool.inner.checkin(object.buffer);
+        self.object = Some(self.pool.fresh_object());
+
+        Ok(())
+    }
+
+    pub fn object_id(&self) -> ObjectId {
+        self.object().id
+    }
+}
+
+impl<C> Drop for PoolRef<C> {
+    fn drop(&mut self) {
+        if let Some(object) = self.object.take() {
+            self.pool.inner.checkin(object.buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ReadObject;
+    use std::collections::HashMap;
+    use std::thread;
+    use std::time::Duration;
+
+    /// In-memory stand-in for a real `Backend`: keeps written objects
+    /// around by id instead of persisting them anywhere.
+    #[derive(Default)]
+    struct FakeBackend(Mutex<HashMap<ObjectId, WriteObject>>);
+
+    impl Backend for FakeBackend {
+        fn write_object(&self, object: &WriteObject) -> core::result::Result<(), BackendError> {
+            self.0.lock().unwrap().insert(object.id, object.clone());
+            Ok(())
+        }
+
+        fn read_object(&self, id: &ObjectId) -> core::result::Result<ReadObject, BackendError> {
+            let objects = self.0.lock().unwrap();
+            let object = objects.get(id).expect("FakeBackend: no object with this id");
+            Ok(ReadObject::from(object))
+        }
+    }
+
+    /// No-op stand-in for a real `CryptoProvider`: fills "randomness"
+    /// with a fixed pattern and passes chunk bytes through unchanged,
+    /// since these tests exercise pool plumbing, not cryptography.
+    #[derive(Clone, Default)]
+    struct FakeCrypto;
+
+    impl Random for FakeCrypto {
+        fn fill(&self, buf: &mut [u8]) {
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+        }
+    }
+
+    impl CryptoProvider for FakeCrypto {
+        fn encrypt_chunk(&self, _object: &WriteObject, _hash: &CryptoDigest, _data: &mut Vec<u8>) -> Tag {
+            Tag::default()
+        }
+
+        fn decrypt_chunk(
+            &self,
+            _object: &ReadObject,
+            _hash: &CryptoDigest,
+            _tag: &Tag,
+            _data: &mut [u8],
+        ) -> core::result::Result<(), CryptoError> {
+            Ok(())
+        }
+    }
+
+    fn test_pool(size: usize) -> WriterPool<FakeCrypto> {
+        WriterPool::new(size, Arc::new(FakeBackend::default()), FakeCrypto)
+    }
+
+    #[test]
+    fn store_chunk_then_flush_round_trips_through_backend() {
+        let backend = Arc::new(FakeBackend::default());
+        let pool = WriterPool::new(2, backend.clone(), FakeCrypto);
+        let mut writer = pool.writer();
+
+        let hash = CryptoDigest::default();
+        let pointer = writer.store_chunk(&hash, b"hello pool").unwrap();
+        let id = writer.object_id();
+        writer.flush().unwrap();
+
+        assert_eq!(pointer.size as usize, b"hello pool".len());
+        assert_eq!(pointer.file, id);
+        assert!(backend.0.lock().unwrap().contains_key(&id));
+    }
+
+    #[test]
+    fn flushing_recycles_the_spent_buffer_back_into_the_pool() {
+        // Pool of one buffer: `writer` holds it, and a second writer
+        // can only be created once `flush` checks it back in.
+        let pool = test_pool(1);
+        let mut writer = pool.writer();
+        writer.store_chunk(&CryptoDigest::default(), b"a").unwrap();
+        writer.flush().unwrap();
+
+        // If flush leaked the buffer (or checked out its replacement
+        // before checking the old one in), the pool would now be
+        // empty and this would block forever.
+        let mut other = pool.writer();
+        other.store_chunk(&CryptoDigest::default(), b"b").unwrap();
+    }
+
+    #[test]
+    fn a_second_writer_blocks_until_the_first_is_dropped() {
+        let pool = test_pool(1);
+        let first = pool.writer();
+
+        let blocked_pool = pool.clone();
+        let handle = thread::spawn(move || {
+            // Blocks in `checkout` until `first` is dropped below and
+            // returns its buffer to the pool.
+            blocked_pool.writer()
+        });
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(
+            !handle.is_finished(),
+            "writer() returned before the pool had a free buffer"
+        );
+
+        drop(first);
+
+        handle
+            .join()
+            .expect("writer() should unblock once a buffer is checked in");
+    }
+}
@@ -0,0 +1,189 @@
+//! Decrypting read path: the counterpart to [`super::ObjectStore`] that
+//! turns a [`ChunkPointer`] back into plaintext bytes.
+
+use super::{ObjectId, ReadObject, Result};
+use crate::backends::{Backend, BackendError};
+use crate::chunks::ChunkPointer;
+use crate::compress;
+use crate::crypto::*;
+
+use std::sync::Arc;
+
+pub trait ObjectReader: Send {
+    fn read_chunk(&mut self, pointer: &ChunkPointer, target: &mut [u8]) -> Result<usize>;
+}
+
+/// Decrypting counterpart to [`super::Storage`]: fetches the object a
+/// [`ChunkPointer`] names, verifies and decrypts the chunk it points
+/// at, decompresses it, and writes the plaintext into the caller's
+/// buffer.
+///
+/// Keeps the most recently fetched object cached so that reading many
+/// chunks in a row out of the same object doesn't re-fetch and
+/// re-buffer it from the backend each time.
+pub struct AEADReader<C> {
+    backend: Arc<dyn Backend>,
+    crypto: C,
+    cache: Option<ReadObject>,
+}
+
+impl<C> AEADReader<C>
+where
+    C: CryptoProvider,
+{
+    pub fn new(backend: Arc<dyn Backend>, crypto: C) -> AEADReader<C> {
+        AEADReader {
+            backend,
+            crypto,
+            cache: None,
+        }
+    }
+
+    fn object(&mut self, id: ObjectId) -> Result<&ReadObject> {
+        let cached = matches!(&self.cache, Some(object) if object.id == id);
+
+        if !cached {
+            let object = self.backend.read_object(&id)?;
+            object.parse_header()?;
+            self.cache = Some(object);
+        }
+
+        Ok(self.cache.as_ref().expect("just populated the cache"))
+    }
+}
+
+impl<C> ObjectReader for AEADReader<C>
+where
+    C: CryptoProvider + Send,
+{
+    fn read_chunk(&mut self, pointer: &ChunkPointer, target: &mut [u8]) -> Result<usize> {
+        let object = self.object(pointer.file)?;
+
+        let start = pointer.offs as usize;
+        let end = start + pointer.size as usize;
+        let bytes = object.as_ref();
+
+        if end > bytes.len() {
+            return Err(super::ObjectError::CorruptObject {
+                offs: pointer.offs,
+                end: end as u32,
+                len: bytes.len(),
+            });
+        }
+
+        let mut buf = bytes[start..end].to_vec();
+
+        self.crypto
+            .decrypt_chunk(object, &pointer.hash, &pointer.tag, &mut buf)?;
+
+        let plaintext = compress::unblock(&buf)?;
+
+        if target.len() < plaintext.len() {
+            return Err(super::ObjectError::BufferTooSmall {
+                min_size: plaintext.len(),
+            });
+        }
+
+        target[..plaintext.len()].copy_from_slice(&plaintext);
+
+        Ok(plaintext.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{ObjectStore, Storage, WriteObject};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// In-memory stand-in for a real `Backend`: keeps written objects
+    /// around by id instead of persisting them anywhere.
+    #[derive(Default)]
+    struct FakeBackend(Mutex<HashMap<ObjectId, WriteObject>>);
+
+    impl Backend for FakeBackend {
+        fn write_object(&self, object: &WriteObject) -> core::result::Result<(), BackendError> {
+            self.0.lock().unwrap().insert(object.id, object.clone());
+            Ok(())
+        }
+
+        fn read_object(&self, id: &ObjectId) -> core::result::Result<ReadObject, BackendError> {
+            let objects = self.0.lock().unwrap();
+            let object = objects.get(id).expect("FakeBackend: no object with this id");
+            Ok(ReadObject::from(object))
+        }
+    }
+
+    /// No-op stand-in for a real `CryptoProvider`: fills "randomness"
+    /// with a fixed pattern and passes chunk bytes through unchanged,
+    /// since these tests exercise the store/read plumbing, not
+    /// cryptography.
+    #[derive(Clone, Default)]
+    struct FakeCrypto;
+
+    impl Random for FakeCrypto {
+        fn fill(&self, buf: &mut [u8]) {
+            for b in buf.iter_mut() {
+                *b = 0;
+            }
+        }
+    }
+
+    impl CryptoProvider for FakeCrypto {
+        fn encrypt_chunk(&self, _object: &WriteObject, _hash: &CryptoDigest, _data: &mut Vec<u8>) -> Tag {
+            Tag::default()
+        }
+
+        fn decrypt_chunk(
+            &self,
+            _object: &ReadObject,
+            _hash: &CryptoDigest,
+            _tag: &Tag,
+            _data: &mut [u8],
+        ) -> core::result::Result<(), CryptoError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn store_chunk_then_read_chunk_round_trips_the_plaintext() {
+        let backend = Arc::new(FakeBackend::default());
+        let mut store = Storage::new(backend.clone(), FakeCrypto);
+        let mut reader = AEADReader::new(backend, FakeCrypto);
+
+        let hash = CryptoDigest::default();
+        let plaintext = b"round trip me through storage and back";
+        let pointer = store.store_chunk(&hash, plaintext).unwrap();
+        store.flush().unwrap();
+
+        let mut out = vec![0u8; plaintext.len()];
+        let n = reader.read_chunk(&pointer, &mut out).unwrap();
+
+        assert_eq!(n, plaintext.len());
+        assert_eq!(&out[..n], &plaintext[..]);
+    }
+
+    #[test]
+    fn read_chunk_rejects_a_pointer_past_the_end_of_the_object() {
+        let backend = Arc::new(FakeBackend::default());
+        let mut store = Storage::new(backend.clone(), FakeCrypto);
+        let mut reader = AEADReader::new(backend, FakeCrypto);
+
+        let hash = CryptoDigest::default();
+        let pointer = store.store_chunk(&hash, b"short").unwrap();
+        store.flush().unwrap();
+
+        let corrupt = Arc::new(ChunkPointer {
+            offs: pointer.offs,
+            size: pointer.size + 1_000_000,
+            ..Default::default()
+        });
+
+        let mut out = vec![0u8; 1_000_000];
+        assert!(matches!(
+            reader.read_chunk(&corrupt, &mut out),
+            Err(super::super::ObjectError::CorruptObject { .. })
+        ));
+    }
+}